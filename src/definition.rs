@@ -0,0 +1,65 @@
+use toml::Value;
+
+/// A condition gating whether a variable is asked about.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Condition {
+    pub name: String,
+    pub value: Value,
+}
+
+/// A single question asked (or answered from a file) during generation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Variable {
+    /// The name the value is bound to in the Tera context.
+    pub name: String,
+    /// The default value, also used to infer the expected type.
+    pub default: Value,
+    /// The prompt shown to the user.
+    pub prompt: String,
+    /// The allowed values, if the variable is a closed choice.
+    #[serde(default)]
+    pub choices: Option<Vec<Value>>,
+    /// A regex a string answer must match.
+    #[serde(default)]
+    pub validation: Option<String>,
+    /// Only ask this question when another variable has a given value.
+    #[serde(default)]
+    pub only_if: Option<Condition>,
+}
+
+/// Paths to delete from the output when a variable takes a given value.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Cleanup {
+    pub name: String,
+    pub value: Value,
+    pub paths: Vec<String>,
+}
+
+/// Render a template file once per element of an array variable.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Iterate {
+    /// A glob, relative to the template root, matching the files to fan out.
+    pub path: String,
+    /// The name of the array-valued variable to iterate over.
+    pub variable: String,
+    /// The name the current element is bound to while rendering.
+    pub binding: String,
+}
+
+/// The parsed `template.toml`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TemplateDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    #[serde(default)]
+    pub copy_without_render: Vec<String>,
+    #[serde(default)]
+    pub cleanup: Vec<Cleanup>,
+    #[serde(default)]
+    pub iterate: Vec<Iterate>,
+}
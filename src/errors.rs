@@ -0,0 +1,85 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use tera;
+
+/// The result type returned throughout the crate.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// Every kind of error kickstart can run into.
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// An IO error, along with the path that triggered it (empty when built
+    /// from a bare `io::Error` through `From`).
+    Io { err: io::Error, path: PathBuf },
+    /// A template failed to render; `path` points at the offending file when known.
+    Tera { err: tera::Error, path: Option<PathBuf> },
+    /// A VCS command (clone/checkout/submodule) failed.
+    Git,
+    /// The template folder has no `template.toml`.
+    MissingTemplateDefinition,
+    /// The `template.toml`, a template `.gitignore` or an answers file could
+    /// not be parsed, or an `[[iterate]]` entry names a non-array variable.
+    InvalidTemplate,
+    /// A pre-supplied answer was missing or did not satisfy the variable's constraints.
+    InvalidAnswer { name: String },
+    /// Two outputs resolved to the same path during generation.
+    DuplicateOutput { path: PathBuf },
+    /// `GenerationMode::Verify` found files that differ from the template.
+    VerifyFailed { paths: Vec<PathBuf> },
+}
+
+/// The error type returned throughout the crate.
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+}
+
+/// Build an `Error` from an `ErrorKind`.
+pub fn new_error(kind: ErrorKind) -> Error {
+    Error { kind }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ErrorKind::Io { ref err, ref path } => {
+                write!(f, "IO error on {}: {}", path.display(), err)
+            }
+            ErrorKind::Tera { ref err, ref path } => match *path {
+                Some(ref p) => write!(f, "Failed to render {}: {}", p.display(), err),
+                None => write!(f, "Failed to render a template: {}", err),
+            },
+            ErrorKind::Git => write!(f, "A version control command failed"),
+            ErrorKind::MissingTemplateDefinition => write!(f, "The template has no template.toml"),
+            ErrorKind::InvalidTemplate => write!(f, "The template or the answers file is invalid"),
+            ErrorKind::InvalidAnswer { ref name } => {
+                write!(f, "Missing or invalid value for variable `{}`", name)
+            }
+            ErrorKind::DuplicateOutput { ref path } => {
+                write!(f, "Several outputs resolved to {}", path.display())
+            }
+            ErrorKind::VerifyFailed { ref paths } => {
+                write!(f, "{} file(s) differ from the template:", paths.len())?;
+                for p in paths {
+                    write!(f, "\n  {}", p.display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        "kickstart error"
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error { kind: ErrorKind::Io { err, path: PathBuf::new() } }
+    }
+}
@@ -0,0 +1,18 @@
+extern crate glob;
+extern crate globset;
+extern crate rayon;
+extern crate regex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate tera;
+extern crate toml;
+extern crate walkdir;
+
+pub mod definition;
+pub mod errors;
+pub mod prompt;
+pub mod template;
+pub mod utils;
+pub mod vcs;
@@ -0,0 +1,65 @@
+extern crate kickstart;
+
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+use kickstart::template::{load_answers, GenerationAction, GenerationMode, Template};
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: kickstart <template> [--output-dir DIR] [--dry-run|--verify] \
+         [--answers FILE] [--strict]"
+    );
+    process::exit(2);
+}
+
+fn run() -> kickstart::errors::Result<()> {
+    let mut args = env::args().skip(1);
+
+    let mut input: Option<String> = None;
+    let mut output_dir = PathBuf::from(".");
+    let mut mode = GenerationMode::Overwrite;
+    let mut answers_path: Option<PathBuf> = None;
+    let mut strict = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--output-dir" => output_dir = PathBuf::from(args.next().unwrap_or_else(|| usage())),
+            "--dry-run" => mode = GenerationMode::DryRun,
+            "--verify" => mode = GenerationMode::Verify,
+            "--answers" => answers_path = Some(PathBuf::from(args.next().unwrap_or_else(|| usage()))),
+            "--strict" => strict = true,
+            _ if input.is_none() => input = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let input = input.unwrap_or_else(|| usage());
+
+    let answers = match answers_path {
+        Some(ref path) => load_answers(path)?,
+        None => HashMap::new(),
+    };
+
+    let template = Template::from_input(&input)?;
+    let actions = template.generate(&output_dir, mode, &answers, strict)?;
+
+    for action in &actions {
+        match *action {
+            GenerationAction::Create(ref p) => println!("create  {}", p.display()),
+            GenerationAction::Overwrite(ref p) => println!("update  {}", p.display()),
+            GenerationAction::Unchanged(ref p) => println!("unchanged  {}", p.display()),
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
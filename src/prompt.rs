@@ -0,0 +1,84 @@
+use std::io::{self, Write};
+
+use regex::Regex;
+use toml::Value;
+
+use errors::Result;
+
+/// Read a line from stdin, trimming the trailing newline.
+fn read_line() -> Result<String> {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim_end().to_string())
+}
+
+/// Ask for a string, re-prompting until the optional validation regex matches.
+pub fn ask_string(prompt: &str, default: &str, validation: &Option<String>) -> Result<String> {
+    let re = validation.as_ref().map(|pattern| Regex::new(pattern).unwrap());
+
+    loop {
+        print!("{} [{}]: ", prompt, default);
+        io::stdout().flush()?;
+        let input = read_line()?;
+        let value = if input.is_empty() { default.to_string() } else { input };
+
+        match re {
+            Some(ref re) if !re.is_match(&value) => {
+                println!("The value does not match {}", validation.as_ref().unwrap());
+                continue;
+            }
+            _ => return Ok(value),
+        }
+    }
+}
+
+/// Ask a yes/no question.
+pub fn ask_bool(prompt: &str, default: bool) -> Result<bool> {
+    loop {
+        print!("{} [{}]: ", prompt, if default { "Y/n" } else { "y/N" });
+        io::stdout().flush()?;
+        let input = read_line()?;
+        match input.to_lowercase().as_ref() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("Please answer with 'y' or 'n'"),
+        }
+    }
+}
+
+/// Ask for an integer.
+pub fn ask_integer(prompt: &str, default: i64) -> Result<i64> {
+    loop {
+        print!("{} [{}]: ", prompt, default);
+        io::stdout().flush()?;
+        let input = read_line()?;
+        if input.is_empty() {
+            return Ok(default);
+        }
+        match input.parse::<i64>() {
+            Ok(i) => return Ok(i),
+            Err(_) => println!("Please enter a whole number"),
+        }
+    }
+}
+
+/// Ask the user to pick one of the allowed choices.
+pub fn ask_choices(prompt: &str, default: &Value, choices: &[Value]) -> Result<Value> {
+    loop {
+        println!("{}", prompt);
+        for (i, choice) in choices.iter().enumerate() {
+            println!("  {}: {}", i + 1, choice);
+        }
+        print!("Choose [{}]: ", default);
+        io::stdout().flush()?;
+        let input = read_line()?;
+        if input.is_empty() {
+            return Ok(default.clone());
+        }
+        match input.parse::<usize>() {
+            Ok(i) if i >= 1 && i <= choices.len() => return Ok(choices[i - 1].clone()),
+            _ => println!("Please pick one of the listed numbers"),
+        }
+    }
+}
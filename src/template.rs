@@ -1,21 +1,24 @@
 use std::env;
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Read;
 use std::fs::{self, File};
 use std::str;
-use std::process::Command;
 
 use toml::{self, Value};
 use tera::{Tera, Context};
 use walkdir::WalkDir;
 use glob::Pattern;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use rayon::prelude::*;
 
 use errors::{Result, ErrorKind, new_error};
 use prompt::{ask_string, ask_bool, ask_choices, ask_integer};
 use utils::{Source, get_source, read_file, write_file, create_directory};
 use utils::{is_vcs, is_binary};
-use definition::TemplateDefinition;
+use definition::{TemplateDefinition, Iterate, Variable};
+use regex::Regex;
+use vcs::{Backend, Git, Mercurial};
 
 
 #[derive(Debug, PartialEq)]
@@ -24,42 +27,353 @@ pub struct Template {
     path: PathBuf,
 }
 
+/// How `Template::generate` should treat the output directory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GenerationMode {
+    /// Write every rendered file, overwriting anything already present.
+    Overwrite,
+    /// Render everything in memory but write nothing, returning the actions
+    /// that would have been taken.
+    DryRun,
+    /// Render everything in memory and error if any file differs from the one
+    /// already on disk, useful to check in CI that a project is still in sync
+    /// with its template.
+    Verify,
+}
+
+/// What `generate` did (or would do in `DryRun`) for a single output file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenerationAction {
+    /// The file does not exist yet and would be created.
+    Create(PathBuf),
+    /// The file exists and its contents would change.
+    Overwrite(PathBuf),
+    /// The file exists and its contents are identical.
+    Unchanged(PathBuf),
+}
+
+/// A single `.gitignore` rule, compiled from one line of a gitignore file.
+///
+/// Rules are evaluated in order, most deeply-nested gitignore last, so nested
+/// overrides win. The last matching rule decides: a plain pattern excludes
+/// the entry, a `!pattern` negation brings it back.
+struct IgnoreRule {
+    /// Directory containing the gitignore, relative to the template root.
+    base: PathBuf,
+    set: GlobSet,
+    negated: bool,
+    /// A trailing-slash pattern (`build/`) only matches directories, never a
+    /// regular file of the same name.
+    dir_only: bool,
+}
+
+/// Collect every `.gitignore` from the template root down and compile their
+/// patterns into ordered rules. The returned vec is sorted so the shallowest
+/// gitignore comes first and the most deeply-nested one last.
+fn build_gitignore_rules(root: &Path) -> Result<Vec<IgnoreRule>> {
+    let mut rules = Vec::new();
+
+    let walker = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| !is_vcs(e))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name() == ".gitignore");
+
+    let mut files: Vec<PathBuf> = walker.map(|e| e.path().to_path_buf()).collect();
+    // Shallowest first so nested gitignores are appended (and thus evaluated) last.
+    files.sort_by_key(|p| p.components().count());
+
+    for file in files {
+        let base = file
+            .parent()
+            .unwrap()
+            .strip_prefix(root)
+            .unwrap()
+            .to_path_buf();
+        let contents = read_file(&file)?;
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let negated = line.starts_with('!');
+            let pattern = if negated { &line[1..] } else { line };
+            // A trailing `/` marks a directory-only pattern.
+            let dir_only = pattern.ends_with('/');
+            let pattern = pattern.trim_end_matches('/');
+            let leading = pattern.starts_with('/');
+            let pattern = pattern.trim_start_matches('/');
+            if pattern.is_empty() {
+                continue;
+            }
+            // Like git: a separator at the start or middle anchors the pattern to
+            // the gitignore's directory; a pattern with no separator matches at
+            // any depth below it.
+            let anchored = leading || pattern.contains('/');
+
+            let mut builder = GlobSetBuilder::new();
+            let globs = if anchored {
+                vec![pattern.to_string(), format!("{}/**", pattern)]
+            } else {
+                vec![
+                    pattern.to_string(),
+                    format!("**/{}", pattern),
+                    format!("{}/**", pattern),
+                    format!("**/{}/**", pattern),
+                ]
+            };
+            for g in globs {
+                // `*` must not cross a path separator, matching git's own
+                // semantics; `**` still does via the glob itself.
+                let glob = GlobBuilder::new(&g)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|_| new_error(ErrorKind::InvalidTemplate))?;
+                builder.add(glob);
+            }
+
+            rules.push(IgnoreRule {
+                base: base.clone(),
+                set: builder.build().map_err(|_| new_error(ErrorKind::InvalidTemplate))?,
+                negated,
+                dir_only,
+            });
+        }
+    }
+
+    Ok(rules)
+}
+
+/// Whether `rel_path` (relative to the template root) is excluded by the
+/// collected gitignore rules. The last matching rule wins, so a nested
+/// negation can whitelist a path a parent gitignore excluded.
+fn is_gitignored(rules: &[IgnoreRule], rel_path: &Path, is_dir: bool) -> bool {
+    let mut excluded = false;
+    for rule in rules {
+        if rule.dir_only && !is_dir {
+            continue;
+        }
+        if let Ok(sub) = rel_path.strip_prefix(&rule.base) {
+            if rule.set.is_match(sub) {
+                excluded = !rule.negated;
+            }
+        }
+    }
+    excluded
+}
+
+/// Turn `input` into a filesystem-safe slug: lowercase, every run of
+/// non-alphanumeric characters collapsed to a single `-`, with no leading or
+/// trailing dash.
+fn slugify(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut pending_dash = false;
+    for c in input.chars() {
+        if c.is_alphanumeric() {
+            if pending_dash && !out.is_empty() {
+                out.push('-');
+            }
+            pending_dash = false;
+            out.extend(c.to_lowercase());
+        } else {
+            pending_dash = true;
+        }
+    }
+    out
+}
+
+/// Slugify the file name of a rendered fan-out path while keeping it usable.
+/// Only the final component is slugified, and only its stem: the extension is
+/// preserved so `service_{{ name }}.rs` produces a valid `.rs` file rather than
+/// `service-name-rs`. Directory components are left exactly as Tera rendered
+/// them so they line up with the directories the first pass already created.
+fn slugify_path(rendered: &str) -> PathBuf {
+    let path = Path::new(rendered);
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => return PathBuf::from(rendered),
+    };
+    let slugged = match name.rfind('.') {
+        Some(dot) => {
+            let (stem, ext) = name.split_at(dot);
+            format!("{}{}", slugify(stem), ext)
+        }
+        None => slugify(&name),
+    };
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.join(slugged),
+        _ => PathBuf::from(slugged),
+    }
+}
+
+
+/// Whether a pre-supplied `value` satisfies the same constraints the
+/// interactive path enforces for `var`: its `choices` list if present,
+/// otherwise a matching TOML type and, for strings, the `validation` regex.
+fn is_valid_answer(var: &Variable, value: &Value) -> bool {
+    if let Some(ref choices) = var.choices {
+        return choices.contains(value);
+    }
+
+    match (&var.default, value) {
+        (Value::Boolean(_), Value::Boolean(_)) => true,
+        (Value::Integer(_), Value::Integer(_)) => true,
+        (Value::Array(_), Value::Array(_)) => true,
+        (Value::String(_), Value::String(s)) => match var.validation {
+            Some(ref pattern) => Regex::new(pattern).map(|re| re.is_match(s)).unwrap_or(false),
+            None => true,
+        },
+        _ => false,
+    }
+}
+
+/// Load a pre-supplied answers map from a TOML or JSON file, selected by the
+/// file extension (anything other than `.json` is parsed as TOML).
+pub fn load_answers(path: &Path) -> Result<HashMap<String, Value>> {
+    let content = read_file(&path.to_path_buf())?;
+    let map = if path.extension().and_then(|e| e.to_str()) == Some("json") {
+        serde_json::from_str(&content).map_err(|_| new_error(ErrorKind::InvalidTemplate))?
+    } else {
+        toml::from_str(&content).map_err(|_| new_error(ErrorKind::InvalidTemplate))?
+    };
+    Ok(map)
+}
+
+/// A single file to render, with its destination resolved up front so that
+/// collisions can be detected before any file is written.
+struct FileJob {
+    /// The source file in the template tree.
+    src: PathBuf,
+    /// The fully resolved output path.
+    dest: PathBuf,
+    /// For fanned-out files, the loop binding and the element to insert into
+    /// the context before rendering the contents.
+    item: Option<(String, Value)>,
+}
+
+/// Render (or, outside `Overwrite`, just compute) a single output file at the
+/// pre-resolved `dest` and report what happened to it.
+fn render_file(
+    src: &Path,
+    dest: &Path,
+    context: &Context,
+    patterns: &[Pattern],
+    mode: GenerationMode,
+) -> Result<GenerationAction> {
+    // Only pass non-binary files or the files not matching the copy_without_render patterns through Tera
+    let mut f = File::open(src)?;
+    let mut buffer = Vec::new();
+    f.read_to_end(&mut buffer)?;
+
+    let no_render = patterns.iter().any(|p| p.matches_path(dest));
+
+    // Files copied verbatim keep their bytes; everything else goes through Tera.
+    let copy_verbatim = no_render || is_binary(&buffer);
+    let new_contents = if copy_verbatim {
+        buffer
+    } else {
+        Tera::one_off(str::from_utf8(&buffer).unwrap(), context, false)
+            .map_err(|err| new_error(ErrorKind::Tera { err, path: Some(src.to_path_buf()) }))?
+            .into_bytes()
+    };
+
+    // Figure out what this write represents relative to the existing file.
+    let action = if !dest.exists() {
+        GenerationAction::Create(dest.to_path_buf())
+    } else {
+        let existing = fs::read(dest)
+            .map_err(|err| new_error(ErrorKind::Io { err, path: dest.to_path_buf() }))?;
+        if existing == new_contents {
+            GenerationAction::Unchanged(dest.to_path_buf())
+        } else {
+            GenerationAction::Overwrite(dest.to_path_buf())
+        }
+    };
+
+    if mode == GenerationMode::Overwrite {
+        // Fanned-out names can resolve into a subdirectory that no static
+        // template entry created, so make sure the parent exists before writing.
+        if let Some(parent) = dest.parent() {
+            create_directory(&parent.to_path_buf())?;
+        }
+        if copy_verbatim {
+            fs::copy(src, dest)
+                .map_err(|err| new_error(ErrorKind::Io { err, path: src.to_path_buf() }))?;
+        } else {
+            write_file(&dest.to_path_buf(), str::from_utf8(&new_contents).unwrap())?;
+        }
+    }
+
+    Ok(action)
+}
+
 impl Template {
     pub fn from_input(input: &str) -> Result<Template> {
+        // A `@ref` suffix pins the checkout to a branch, tag or commit. It is only
+        // honored for remote sources; local paths are left untouched so a folder
+        // whose name contains `@` still resolves correctly. Only the last `@`
+        // counts, and only after the last `/` or `:`, so scp-like `user@host:repo`
+        // remotes keep their `@`.
+        let split_ref = |remote: &str| -> (String, Option<String>) {
+            match remote.rfind('@') {
+                Some(idx) if idx > remote.rfind(['/', ':']).unwrap_or(0) => {
+                    (remote[..idx].to_string(), Some(remote[idx + 1..].to_string()))
+                }
+                _ => (remote.to_string(), None),
+            }
+        };
+
         match get_source(input) {
-            Source::Git(remote) => Template::from_git(&remote),
+            Source::Git(remote) => {
+                let (remote, reference) = split_ref(&remote);
+                Template::from_git(&remote, reference.as_deref())
+            }
+            Source::Mercurial(remote) => {
+                let (remote, reference) = split_ref(&remote);
+                Template::from_vcs(&Mercurial, &remote, reference.as_deref())
+            }
             Source::Local(path) => Ok(Template::from_local(&path)),
         }
     }
 
-    pub fn from_git(remote: &str) -> Result<Template> {
-        // Clone the remote in git first in /tmp
+    pub fn from_git(remote: &str, reference: Option<&str>) -> Result<Template> {
+        Template::from_vcs(&Git, remote, reference)
+    }
+
+    /// Clone `remote` with the given `backend` into a temporary folder, pulling in
+    /// any submodules and checking out `reference` if one was requested.
+    pub fn from_vcs(backend: &dyn Backend, remote: &str, reference: Option<&str>) -> Result<Template> {
+        // Clone the remote first in /tmp
         let mut tmp = env::temp_dir();
-        tmp.push(remote.split("/").last().unwrap_or_else(|| "kickstart"));
+        tmp.push(remote.split('/').next_back().unwrap_or("kickstart"));
         if tmp.exists() {
             fs::remove_dir_all(&tmp)?;
         }
         println!("Cloning the repository in your temporary folder...");
 
-        // Use git command rather than git2 as it seems there are some issues building it
-        // on some platforms:
-        // https://www.reddit.com/r/rust/comments/92mbk5/kickstart_a_scaffolding_tool_to_get_new_projects/e3ahegw
-        Command::new("git")
-            .current_dir(&tmp)
-            .args(&["clone", remote, &format!("{}", tmp.display())])
-            .output()
-            .map_err(|_| new_error(ErrorKind::Git))?;
+        backend.clone(remote, &tmp)?;
+        if let Some(reference) = reference {
+            backend.checkout(&tmp, reference)?;
+        }
+        backend.init_submodules(&tmp)?;
 
         Ok(Template::from_local(&tmp))
     }
 
-    pub fn from_local(path: &PathBuf) -> Template {
+    pub fn from_local(path: &Path) -> Template {
         Template {
             path: path.to_path_buf(),
         }
     }
 
-    fn ask_questions(&self, def: &TemplateDefinition) -> Result<HashMap<String, Value>> {
+    fn ask_questions(
+        &self,
+        def: &TemplateDefinition,
+        answers: &HashMap<String, Value>,
+        strict: bool,
+    ) -> Result<HashMap<String, Value>> {
         // Tera context doesn't expose a way to get value from a context
         // so we store them in another hashmap
         let mut vals = HashMap::new();
@@ -74,6 +388,20 @@ impl Template {
                 }
             }
 
+            // Use a pre-supplied answer when one is available and valid. Missing
+            // or invalid entries fall back to prompting, or are an error in
+            // strict mode.
+            if let Some(provided) = answers.get(&var.name) {
+                if is_valid_answer(var, provided) {
+                    vals.insert(var.name.clone(), provided.clone());
+                    continue;
+                } else if strict {
+                    return Err(new_error(ErrorKind::InvalidAnswer { name: var.name.clone() }));
+                }
+            } else if strict {
+                return Err(new_error(ErrorKind::InvalidAnswer { name: var.name.clone() }));
+            }
+
             if let Some(ref choices) = var.choices {
                 let res = ask_choices(&var.prompt, &var.default, choices)?;
                 vals.insert(var.name.clone(), res);
@@ -87,7 +415,7 @@ impl Template {
                     continue;
                 },
                 Value::String(s) => {
-                    let res = ask_string(&var.prompt, &s, &var.validation)?;
+                    let res = ask_string(&var.prompt, s, &var.validation)?;
                     vals.insert(var.name.clone(), Value::String(res));
                     continue;
                 },
@@ -96,14 +424,23 @@ impl Template {
                     vals.insert(var.name.clone(), Value::Integer(res));
                     continue;
                 },
-                _ => panic!("Unsupported TOML type in a question: {:?}", var.default)
+                // Types we can't prompt for interactively (e.g. arrays for
+                // fan-out) must come from an answers file; fail loudly rather
+                // than panicking when one is missing.
+                _ => return Err(new_error(ErrorKind::InvalidAnswer { name: var.name.clone() })),
             }
         }
 
         Ok(vals)
     }
 
-    pub fn generate(&self, output_dir: &PathBuf) -> Result<()> {
+    pub fn generate(
+        &self,
+        output_dir: &PathBuf,
+        mode: GenerationMode,
+        answers: &HashMap<String, Value>,
+        strict: bool,
+    ) -> Result<Vec<GenerationAction>> {
         // Get the variables from the user first
         let conf_path = self.path.join("template.toml");
         if !conf_path.exists() {
@@ -113,14 +450,14 @@ impl Template {
         let definition: TemplateDefinition = toml::from_str(&read_file(&conf_path)?)
             .map_err(|_| new_error(ErrorKind::InvalidTemplate))?;
 
-        let variables = self.ask_questions(&definition)?;
+        let variables = self.ask_questions(&definition, answers, strict)?;
         let mut context = Context::new();
         for (key, val) in &variables {
             context.insert(key, val);
         }
 
-        if !output_dir.exists() {
-            create_directory(&output_dir)?;
+        if !output_dir.exists() && mode == GenerationMode::Overwrite {
+            create_directory(output_dir)?;
         }
 
         // Create the glob patterns of files to copy without rendering first, only once
@@ -129,10 +466,31 @@ impl Template {
             .map(|s| Pattern::new(s).unwrap())
             .collect();
 
-        // And now generate the files in the output dir given
+        // Collect the gitignore rules the template ships with so we can reuse
+        // them instead of duplicating globs in template.toml.
+        let gitignore_rules = build_gitignore_rules(&self.path)?;
+
+        // Compile the iterate globs once, pairing each with its binding/variable.
+        let iterate: Vec<(Pattern, &Iterate)> = definition.iterate
+            .iter()
+            .map(|it| (Pattern::new(&it.path).unwrap(), it))
+            .collect();
+
+        // First pass (ordered): create every directory and resolve the output
+        // path of every file up front. Directories must exist before we write
+        // files into them, so this pass stays sequential. Gitignored subtrees
+        // are pruned by `filter_entry` so `WalkDir` never descends into them.
+        let mut jobs: Vec<FileJob> = Vec::new();
+        let mut seen: HashSet<PathBuf> = HashSet::new();
         let walker = WalkDir::new(&self.path)
             .into_iter()
-            .filter_entry(|e| !is_vcs(e))
+            .filter_entry(|e| {
+                if is_vcs(e) {
+                    return false;
+                }
+                let rel = e.path().strip_prefix(&self.path).unwrap_or_else(|_| e.path());
+                !is_gitignored(&gitignore_rules, rel, e.file_type().is_dir())
+            })
             .filter_map(|e| e.ok());
 
         'outer: for entry in walker {
@@ -149,39 +507,93 @@ impl Template {
                 }
             }
 
-            let tpl = Tera::one_off(&path_str, &context, false)
-                .map_err(|err| new_error(ErrorKind::Tera { err, path: None }))?;
-
-            let real_path = output_dir.join(Path::new(&tpl));
-
             if entry.path().is_dir() {
-                create_directory(&real_path)?;
+                if mode == GenerationMode::Overwrite {
+                    let tpl = Tera::one_off(&path_str, &context, false)
+                        .map_err(|err| new_error(ErrorKind::Tera { err, path: Some(entry.path().to_path_buf()) }))?;
+                    create_directory(&output_dir.join(Path::new(&tpl)))?;
+                }
                 continue;
             }
 
-            // Only pass non-binary files or the files not matching the copy_without_render patterns through Tera
-            let mut f = File::open(&entry.path())?;
-            let mut buffer = Vec::new();
-            f.read_to_end(&mut buffer)?;
+            let src = entry.path().to_path_buf();
+            if let Some((_, it)) = iterate.iter().find(|(p, _)| p.matches(&path_str)) {
+                // Fan the file out once per element of the bound array variable.
+                let items = match variables.get(&it.variable) {
+                    Some(Value::Array(items)) => items.clone(),
+                    _ => return Err(new_error(ErrorKind::InvalidTemplate)),
+                };
+                for item in items {
+                    let mut ctx = context.clone();
+                    ctx.insert(&it.binding, &item);
+                    let tpl = Tera::one_off(&path_str, &ctx, false)
+                        .map_err(|err| new_error(ErrorKind::Tera { err, path: Some(src.clone()) }))?;
+                    let dest = output_dir.join(slugify_path(&tpl));
+                    // A fan-out whose filename doesn't vary per item (or that
+                    // collides with another output) would clobber silently.
+                    if !seen.insert(dest.clone()) {
+                        return Err(new_error(ErrorKind::DuplicateOutput { path: dest }));
+                    }
+                    jobs.push(FileJob {
+                        src: src.clone(),
+                        dest,
+                        item: Some((it.binding.clone(), item)),
+                    });
+                }
+            } else {
+                let tpl = Tera::one_off(&path_str, &context, false)
+                    .map_err(|err| new_error(ErrorKind::Tera { err, path: Some(src.clone()) }))?;
+                let dest = output_dir.join(Path::new(&tpl));
+                if !seen.insert(dest.clone()) {
+                    return Err(new_error(ErrorKind::DuplicateOutput { path: dest }));
+                }
+                jobs.push(FileJob { src, dest, item: None });
+            }
+        }
 
-            let no_render = patterns.iter().map(|p| p.matches_path(&real_path)).any(|x| x);
+        // Second pass: render/copy the files in parallel. The Tera Context and the
+        // compiled copy_without_render patterns are read-only during rendering so
+        // they can be shared across threads; errors propagate through the collect.
+        let actions: Vec<GenerationAction> = jobs
+            .par_iter()
+            .map(|job| -> Result<GenerationAction> {
+                match job.item {
+                    Some((ref binding, ref item)) => {
+                        // Bind the current element into a cloned context so each
+                        // output gets its own value without racing the others.
+                        let mut ctx = context.clone();
+                        ctx.insert(binding, item);
+                        render_file(&job.src, &job.dest, &ctx, &patterns, mode)
+                    }
+                    None => render_file(&job.src, &job.dest, &context, &patterns, mode),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-            if no_render || is_binary(&buffer) {
-                fs::copy(&entry.path(), &real_path)
-                    .map_err(|err| new_error(ErrorKind::Io { err, path: entry.path().to_path_buf() }))?;
-                continue;
+        // In Verify mode, surface every file that is out of sync with the template.
+        if mode == GenerationMode::Verify {
+            let differing: Vec<PathBuf> = actions
+                .iter()
+                .filter_map(|a| match a {
+                    GenerationAction::Create(p) | GenerationAction::Overwrite(p) => Some(p.clone()),
+                    GenerationAction::Unchanged(_) => None,
+                })
+                .collect();
+            if !differing.is_empty() {
+                return Err(new_error(ErrorKind::VerifyFailed { paths: differing }));
             }
+        }
 
-            let contents = Tera::one_off(&str::from_utf8(&buffer).unwrap(), &context, false)
-                .map_err(|err| new_error(ErrorKind::Tera {err, path: Some(entry.path().to_path_buf())}))?;
-            write_file(&real_path, &contents)?;
+        // Cleanup deletes files, so only touch the output dir when actually writing.
+        if mode != GenerationMode::Overwrite {
+            return Ok(actions);
         }
 
         for cleanup in &definition.cleanup {
             if let Some(val) = variables.get(&cleanup.name) {
                 if *val == cleanup.value {
                     for p in &cleanup.paths {
-                        let actual_path = Tera::one_off(&p, &context, false)
+                        let actual_path = Tera::one_off(p, &context, false)
                             .map_err(|err| new_error(ErrorKind::Tera { err, path: None }))?;
                         let path_to_delete = output_dir.join(actual_path);
                         if !path_to_delete.exists() {
@@ -199,6 +611,6 @@ impl Template {
             }
         }
 
-        Ok(())
+        Ok(actions)
     }
 }
@@ -0,0 +1,73 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use walkdir::DirEntry;
+
+use errors::{Result, ErrorKind, new_error};
+
+/// Where a template comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// A git remote.
+    Git(String),
+    /// A Mercurial remote (written as `hg+<url>`).
+    Mercurial(String),
+    /// A local folder.
+    Local(PathBuf),
+}
+
+/// Work out where a template input points to. A `hg+` prefix selects Mercurial,
+/// anything that looks like a URL or an scp-like git remote is git, and
+/// everything else is treated as a local path.
+pub fn get_source(input: &str) -> Source {
+    if let Some(rest) = input.strip_prefix("hg+") {
+        return Source::Mercurial(rest.to_string());
+    }
+
+    if input.starts_with("http://")
+        || input.starts_with("https://")
+        || input.starts_with("git@")
+        || input.ends_with(".git")
+    {
+        return Source::Git(input.to_string());
+    }
+
+    Source::Local(PathBuf::from(input))
+}
+
+/// Read a whole file into a `String`.
+pub fn read_file(path: &PathBuf) -> Result<String> {
+    let mut f = File::open(path)
+        .map_err(|err| new_error(ErrorKind::Io { err, path: path.clone() }))?;
+    let mut contents = String::new();
+    f.read_to_string(&mut contents)
+        .map_err(|err| new_error(ErrorKind::Io { err, path: path.clone() }))?;
+    Ok(contents)
+}
+
+/// Write `contents` to `path`, creating it if needed.
+pub fn write_file(path: &PathBuf, contents: &str) -> Result<()> {
+    let mut f = File::create(path)
+        .map_err(|err| new_error(ErrorKind::Io { err, path: path.clone() }))?;
+    f.write_all(contents.as_bytes())
+        .map_err(|err| new_error(ErrorKind::Io { err, path: path.clone() }))?;
+    Ok(())
+}
+
+/// Create a directory and all of its parents.
+pub fn create_directory(path: &PathBuf) -> Result<()> {
+    fs::create_dir_all(path)
+        .map_err(|err| new_error(ErrorKind::Io { err, path: path.clone() }))
+}
+
+/// Whether an entry belongs to a VCS metadata folder we should never render.
+pub fn is_vcs(entry: &DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+    name == ".git" || name == ".hg" || name == ".svn"
+}
+
+/// A very rough heuristic: a file is binary if it contains a NUL byte.
+pub fn is_binary(buffer: &[u8]) -> bool {
+    buffer.contains(&0)
+}
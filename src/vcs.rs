@@ -0,0 +1,100 @@
+use std::path::Path;
+use std::process::Command;
+
+use errors::{Result, ErrorKind, new_error};
+
+/// A version control backend able to fetch a template from a remote and to
+/// pull in any submodules the template is composed of.
+///
+/// Implementing this trait lets third parties register their own DVCS
+/// backend without having to touch the rest of kickstart.
+pub trait Backend {
+    /// Clone `remote` into the local `dest` directory.
+    fn clone(&self, remote: &str, dest: &Path) -> Result<()>;
+
+    /// Initialise and update any submodules present in `dest` after the
+    /// initial clone so templates composed of submodules work.
+    fn init_submodules(&self, dest: &Path) -> Result<()>;
+
+    /// Check out the given ref (branch, tag or commit) in `dest`.
+    fn checkout(&self, dest: &Path, reference: &str) -> Result<()>;
+}
+
+/// The git backend.
+#[derive(Debug)]
+pub struct Git;
+
+impl Backend for Git {
+    fn clone(&self, remote: &str, dest: &Path) -> Result<()> {
+        // Use the git command rather than git2 as it seems there are some issues building it
+        // on some platforms:
+        // https://www.reddit.com/r/rust/comments/92mbk5/kickstart_a_scaffolding_tool_to_get_new_projects/e3ahegw
+        let output = Command::new("git")
+            .args(["clone", remote, &format!("{}", dest.display())])
+            .output()
+            .map_err(|_| new_error(ErrorKind::Git))?;
+        if !output.status.success() {
+            return Err(new_error(ErrorKind::Git));
+        }
+        Ok(())
+    }
+
+    fn init_submodules(&self, dest: &Path) -> Result<()> {
+        let output = Command::new("git")
+            .current_dir(dest)
+            .args(["submodule", "update", "--init", "--recursive"])
+            .output()
+            .map_err(|_| new_error(ErrorKind::Git))?;
+        if !output.status.success() {
+            return Err(new_error(ErrorKind::Git));
+        }
+        Ok(())
+    }
+
+    fn checkout(&self, dest: &Path, reference: &str) -> Result<()> {
+        let output = Command::new("git")
+            .current_dir(dest)
+            .args(["checkout", reference])
+            .output()
+            .map_err(|_| new_error(ErrorKind::Git))?;
+        if !output.status.success() {
+            return Err(new_error(ErrorKind::Git));
+        }
+        Ok(())
+    }
+}
+
+/// The Mercurial backend.
+#[derive(Debug)]
+pub struct Mercurial;
+
+impl Backend for Mercurial {
+    fn clone(&self, remote: &str, dest: &Path) -> Result<()> {
+        let output = Command::new("hg")
+            .args(["clone", remote, &format!("{}", dest.display())])
+            .output()
+            .map_err(|_| new_error(ErrorKind::Git))?;
+        if !output.status.success() {
+            return Err(new_error(ErrorKind::Git));
+        }
+        Ok(())
+    }
+
+    fn init_submodules(&self, _dest: &Path) -> Result<()> {
+        // Mercurial pulls subrepositories in as part of the clone, so there is
+        // nothing extra to do here.
+        Ok(())
+    }
+
+    fn checkout(&self, dest: &Path, reference: &str) -> Result<()> {
+        let output = Command::new("hg")
+            .current_dir(dest)
+            .args(["update", reference])
+            .output()
+            .map_err(|_| new_error(ErrorKind::Git))?;
+        if !output.status.success() {
+            return Err(new_error(ErrorKind::Git));
+        }
+        Ok(())
+    }
+}